@@ -0,0 +1,278 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// the state of the circuit breaker of a DBPool
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BreakerState {
+    /// acquire as usual
+    Closed,
+    /// acquire fail fast with Error::CircuitOpen
+    Open,
+    /// let a few probe acquires through, a success close the breaker and a failure open it again
+    HalfOpen,
+}
+
+/// a state change of the circuit breaker
+#[derive(Debug, Clone)]
+pub struct BreakerEvent {
+    pub from: BreakerState,
+    pub to: BreakerState,
+    /// the consecutive connection failures
+    pub failures: u32,
+    /// the error opening the breaker
+    pub error: Option<Error>,
+}
+
+/// called on every state change of the circuit breaker
+pub type OnBreakerEvent = Arc<dyn Fn(&BreakerEvent) + Send + Sync>;
+
+/// DBPoolOptions::circuit_breaker
+#[derive(Clone)]
+pub struct CircuitBreakerOptions {
+    /// the consecutive connection failures opening the breaker: Error::is_connection_lost,
+    /// or Error::PoolTimeout while the host does not answer a probe(a busy pool is not a failure).
+    /// the probe of a failover is reused, else the probe is bounded by a quarter of connect_timeout
+    pub failure_threshold: u32,
+    /// how long the breaker stay open before it turn half-open
+    pub open_timeout: Duration,
+    /// the probe acquires allowed at once while half-open
+    pub half_open_probes: u32,
+    pub on_event: Option<OnBreakerEvent>,
+}
+
+impl Debug for CircuitBreakerOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CircuitBreakerOptions")
+            .field("failure_threshold", &self.failure_threshold)
+            .field("open_timeout", &self.open_timeout)
+            .field("half_open_probes", &self.half_open_probes)
+            .field("on_event", &self.on_event.is_some())
+            .finish()
+    }
+}
+
+impl Default for CircuitBreakerOptions {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_timeout: Duration::from_secs(30),
+            half_open_probes: 1,
+            on_event: None,
+        }
+    }
+}
+
+impl CircuitBreakerOptions {
+    pub fn new() -> Self {
+        CircuitBreakerOptions::default()
+    }
+
+    pub fn on_event<F>(mut self, f: F) -> Self
+        where
+            F: Fn(&BreakerEvent) + Send + Sync + 'static,
+    {
+        self.on_event = Some(Arc::new(f));
+        self
+    }
+}
+
+/// does the error count as a connection failure, DBPool probes the host on Error::PoolTimeout and calls record_failure()
+fn is_failure(e: &Error) -> bool {
+    e.is_connection_lost()
+}
+
+#[derive(Debug)]
+struct BreakerInner {
+    state: BreakerState,
+    failures: u32,
+    opened_at: Instant,
+    probes: u32,
+}
+
+/// the breaker state shared by all clones of a DBPool, the options are passed in by the caller
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    inner: Mutex<BreakerInner>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(BreakerInner {
+                state: BreakerState::Closed,
+                failures: 0,
+                opened_at: Instant::now(),
+                probes: 0,
+            }),
+        }
+    }
+}
+
+impl CircuitBreaker {
+    pub fn state(&self) -> BreakerState {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).state
+    }
+
+    /// Err(Error::CircuitOpen) if the acquire must fail fast
+    pub fn permit<'a>(&'a self, opt: &'a CircuitBreakerOptions) -> crate::Result<BreakerPermit<'a>> {
+        let mut event = None;
+        let probe = {
+            let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+            if inner.state == BreakerState::Open && inner.opened_at.elapsed() >= opt.open_timeout {
+                event = Some(inner.change(BreakerState::HalfOpen, None));
+            }
+            match inner.state {
+                BreakerState::Closed => false,
+                BreakerState::HalfOpen if inner.probes < opt.half_open_probes.max(1) => {
+                    inner.probes += 1;
+                    true
+                }
+                _ => {
+                    drop(inner);
+                    emit(opt, event);
+                    return Err(Error::CircuitOpen);
+                }
+            }
+        };
+        emit(opt, event);
+        Ok(BreakerPermit {
+            breaker: self,
+            opt,
+            probe,
+        })
+    }
+}
+
+impl BreakerInner {
+    fn change(&mut self, to: BreakerState, error: Option<Error>) -> BreakerEvent {
+        let from = self.state;
+        self.state = to;
+        if to == BreakerState::Open {
+            self.opened_at = Instant::now();
+        }
+        BreakerEvent {
+            from,
+            to,
+            failures: self.failures,
+            error,
+        }
+    }
+}
+
+fn emit(opt: &CircuitBreakerOptions, event: Option<BreakerEvent>) {
+    if let Some(event) = event {
+        log::warn!("[rbatis-core] circuit breaker {:?} -> {:?}", event.from, event.to);
+        if let Some(f) = &opt.on_event {
+            f(&event);
+        }
+    }
+}
+
+/// an acquire let through by the breaker, a dropped permit of a probe frees its slot
+pub struct BreakerPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    opt: &'a CircuitBreakerOptions,
+    probe: bool,
+}
+
+impl BreakerPermit<'_> {
+    /// count the result of the acquire
+    pub fn record<T>(self, result: &crate::Result<T>) {
+        match result {
+            Ok(_) => {
+                let mut event = None;
+                {
+                    let mut inner = self.breaker.inner.lock().unwrap_or_else(|e| e.into_inner());
+                    inner.failures = 0;
+                    if inner.state != BreakerState::Closed {
+                        event = Some(inner.change(BreakerState::Closed, None));
+                    }
+                }
+                emit(self.opt, event);
+            }
+            Err(e) if is_failure(e) => self.record_failure(e),
+            Err(_) => {}
+        }
+    }
+
+    /// count a connection failure, whatever the error is
+    pub fn record_failure(self, e: &Error) {
+        let mut event = None;
+        {
+            let mut inner = self.breaker.inner.lock().unwrap_or_else(|e| e.into_inner());
+            inner.failures += 1;
+            let open = match inner.state {
+                BreakerState::Closed => inner.failures >= self.opt.failure_threshold.max(1),
+                //only a probe open it again, not an acquire started before the breaker opened
+                BreakerState::HalfOpen => self.probe,
+                BreakerState::Open => false,
+            };
+            if open {
+                event = Some(inner.change(BreakerState::Open, Some(e.clone())));
+            }
+        }
+        emit(self.opt, event);
+    }
+}
+
+impl Drop for BreakerPermit<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            let mut inner = self.breaker.inner.lock().unwrap_or_else(|e| e.into_inner());
+            inner.probes = inner.probes.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use crate::db::breaker::CircuitBreaker;
+    use crate::db::{BreakerState, CircuitBreakerOptions};
+    use crate::Error;
+
+    #[test]
+    fn test_breaker() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let sink = events.clone();
+        let mut opt = CircuitBreakerOptions::new().on_event(move |e| sink.lock().unwrap().push((e.from, e.to)));
+        opt.failure_threshold = 2;
+        opt.open_timeout = Duration::from_millis(0);
+        let breaker = CircuitBreaker::default();
+        let lost = || Error::Connection("refused".to_string(), None);
+        breaker.permit(&opt).unwrap().record::<()>(&Err(lost()));
+        //not a connection failure
+        breaker.permit(&opt).unwrap().record::<()>(&Err(Error::from("syntax error")));
+        breaker.permit(&opt).unwrap().record::<()>(&Err(Error::PoolTimeout));
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        breaker.permit(&opt).unwrap().record_failure(&Error::PoolTimeout);
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        let probe = breaker.permit(&opt).unwrap();
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(matches!(breaker.permit(&opt), Err(Error::CircuitOpen)));
+        probe.record::<()>(&Err(lost()));
+        assert_eq!(breaker.state(), BreakerState::Open);
+        //a cancelled probe free the slot
+        drop(breaker.permit(&opt).unwrap());
+        breaker.permit(&opt).unwrap().record(&Ok(()));
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                (BreakerState::Closed, BreakerState::Open),
+                (BreakerState::Open, BreakerState::HalfOpen),
+                (BreakerState::HalfOpen, BreakerState::Open),
+                (BreakerState::Open, BreakerState::HalfOpen),
+                (BreakerState::HalfOpen, BreakerState::Closed),
+            ]
+        );
+    }
+}
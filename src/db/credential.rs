@@ -0,0 +1,113 @@
+use std::fmt::{Debug, Formatter};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use futures_core::future::BoxFuture;
+
+use crate::Error;
+
+/// the username and password of a new physical conn. Debug never print the password
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct Credential {
+    /// None keep the username of the connect option
+    pub username: Option<String>,
+    pub password: String,
+    /// when the password stop working for new conns, e.g. the expiry of an IAM token.
+    /// DBPool does not ask the provider again until then, None ask it whenever a new conn is opened
+    pub expires_at: Option<Instant>,
+}
+
+impl Credential {
+    /// the same username and password, the expiry may differ
+    pub fn same_login(&self, other: &Credential) -> bool {
+        self.username == other.username && self.password == other.password
+    }
+}
+
+impl Debug for Credential {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credential")
+            .field("username", &self.username)
+            .field("password", &"***")
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+/// supply the password of the new physical conns, e.g. a short-lived IAM token or a vault lease.
+/// sqlx fixes the login of a pool when it is built, so DBPool can not pass a credential to each new conn:
+/// it asks the provider when it is about to open a new conn, and switch to a new sqlx pool when the login change.
+/// the conns of the old pool keep working, they are closed once given back.
+/// a provider minting a new password per call must set expires_at, or the pool is switched on every new conn
+pub trait CredentialProvider: Send + Sync {
+    fn credential(&self) -> BoxFuture<'_, crate::Result<Credential>>;
+}
+
+/// read the password from a secret file, the trailing newline is trimmed
+#[derive(Debug, Clone)]
+pub struct FileCredentialProvider {
+    pub path: PathBuf,
+    pub username: Option<String>,
+    /// how long a read password is used before the file is read again, None read it for every new conn
+    pub refresh: Option<Duration>,
+}
+
+impl FileCredentialProvider {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            username: None,
+            refresh: None,
+        }
+    }
+
+    pub fn username(mut self, username: &str) -> Self {
+        self.username = Some(username.to_string());
+        self
+    }
+
+    pub fn refresh(mut self, refresh: Duration) -> Self {
+        self.refresh = Some(refresh);
+        self
+    }
+}
+
+impl CredentialProvider for FileCredentialProvider {
+    fn credential(&self) -> BoxFuture<'_, crate::Result<Credential>> {
+        Box::pin(async move {
+            let password = crate::runtime::fs::read_to_string(&self.path).await.map_err(|e| {
+                Error::from(format!("[rbatis-core] read credential file {} fail: {}!", self.path.display(), e))
+            })?;
+            Ok(Credential {
+                username: self.username.clone(),
+                password: password.trim_end_matches(['\r', '\n']).to_string(),
+                expires_at: self.refresh.map(|x| Instant::now() + x),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::db::{CredentialProvider, FileCredentialProvider};
+
+    #[test]
+    fn test_file_credential() {
+        crate::runtime::task::block_on(async {
+            let path = std::env::temp_dir().join(format!("rbatis_secret_{}", std::process::id()));
+            std::fs::write(&path, "token1\n").unwrap();
+            let provider = FileCredentialProvider::new(&path).username("app");
+            let credential = provider.credential().await.unwrap();
+            assert_eq!(credential.username.as_deref(), Some("app"));
+            assert_eq!(credential.password, "token1");
+            assert!(!format!("{:?}", credential).contains("token1"));
+            assert!(credential.expires_at.is_none());
+            let provider = provider.refresh(std::time::Duration::from_secs(60));
+            assert!(provider.credential().await.unwrap().expires_at.is_some());
+            std::fs::write(&path, "token2").unwrap();
+            assert_eq!(provider.credential().await.unwrap().password, "token2");
+            std::fs::remove_file(&path).unwrap();
+            assert!(provider.credential().await.is_err());
+        });
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::db::{DBPool, DBPoolOptions, DatabaseConfig};
+use crate::Error;
+
+/// the config of DataSources
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DataSourcesConfig {
+    /// the name of the default datasource, may be None if there is only one
+    #[serde(default)]
+    pub default: Option<String>,
+    /// name -> a database url or a DatabaseConfig
+    #[serde(deserialize_with = "deserialize_datasources")]
+    pub datasources: BTreeMap<String, DatabaseConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DataSourceRepr {
+    Url(String),
+    Config(Box<DatabaseConfig>),
+}
+
+fn deserialize_datasources<'de, D>(deserializer: D) -> Result<BTreeMap<String, DatabaseConfig>, D::Error>
+    where
+        D: Deserializer<'de>,
+{
+    let datasources = BTreeMap::<String, DataSourceRepr>::deserialize(deserializer)?;
+    Ok(datasources
+        .into_iter()
+        .map(|(name, repr)| {
+            let config = match repr {
+                DataSourceRepr::Url(url) => DatabaseConfig {
+                    url: Some(url),
+                    ..Default::default()
+                },
+                DataSourceRepr::Config(config) => *config,
+            };
+            (name, config)
+        })
+        .collect())
+}
+
+/// DBPools by name with a default one
+#[derive(Debug, Clone, Default)]
+pub struct DataSources {
+    pub pools: HashMap<String, DBPool>,
+    pub default: Option<String>,
+}
+
+impl DataSources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// connect all datasources of the config, the pool settings of each config override opt.
+    /// the opened pools are closed if any of them fail
+    pub async fn from_config(config: &DataSourcesConfig, opt: &DBPoolOptions) -> crate::Result<Self> {
+        let default = match &config.default {
+            Some(name) => name.clone(),
+            None if config.datasources.len() == 1 => config.datasources.keys().next().cloned().unwrap_or_default(),
+            None => return Err(Error::from("[rbatis-core] DataSourcesConfig default datasource is none!")),
+        };
+        if !config.datasources.contains_key(&default) {
+            return Err(Error::from(format!("[rbatis-core] datasource \"{}\" not exist!", default)));
+        }
+        let mut sources = DataSources::new();
+        for (name, database) in &config.datasources {
+            match database.connect_with(opt).await {
+                Ok(pool) => {
+                    sources.insert(name, pool);
+                }
+                Err(e) => {
+                    log::error!("[rbatis-core] connect datasource \"{}\" fail: {}", name, e);
+                    sources.close().await;
+                    return Err(e);
+                }
+            }
+        }
+        sources.default = Some(default);
+        Ok(sources)
+    }
+
+    /// add a pool, the first added pool is the default. return the replaced pool of the same name
+    pub fn insert(&mut self, name: &str, pool: DBPool) -> Option<DBPool> {
+        if self.default.is_none() {
+            self.default = Some(name.to_string());
+        }
+        self.pools.insert(name.to_string(), pool)
+    }
+
+    /// remove a pool without closing it, the default can not be removed
+    pub fn remove(&mut self, name: &str) -> crate::Result<DBPool> {
+        if self.default.as_deref() == Some(name) {
+            return Err(Error::from(format!("[rbatis-core] can not remove the default datasource \"{}\"!", name)));
+        }
+        self.pools
+            .remove(name)
+            .ok_or_else(|| Error::from(format!("[rbatis-core] datasource \"{}\" not exist!", name)))
+    }
+
+    pub fn set_default(&mut self, name: &str) -> crate::Result<()> {
+        if !self.pools.contains_key(name) {
+            return Err(Error::from(format!("[rbatis-core] datasource \"{}\" not exist!", name)));
+        }
+        self.default = Some(name.to_string());
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> crate::Result<&DBPool> {
+        self.pools
+            .get(name)
+            .ok_or_else(|| Error::from(format!("[rbatis-core] datasource \"{}\" not exist!", name)))
+    }
+
+    /// the default pool
+    pub fn get_default(&self) -> crate::Result<&DBPool> {
+        match &self.default {
+            Some(name) => self.get(name),
+            None => Err(Error::from("[rbatis-core] DataSources is empty!")),
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.pools.keys().map(|x| x.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// close all pools
+    pub async fn close(&self) {
+        for pool in self.pools.values() {
+            pool.close().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::db::{DBPoolOptions, DataSources, DataSourcesConfig};
+
+    #[test]
+    fn test_datasources() {
+        crate::runtime::task::block_on(async {
+            let config: DataSourcesConfig = serde_json::from_str(r#"{
+                "default": "order",
+                "datasources": {
+                    "order": "sqlite::memory:",
+                    "user": {"driver": "sqlite", "database": ":memory:", "pool": {"max_connections": 2}}
+                }
+            }"#).unwrap();
+            let mut sources = DataSources::from_config(&config, &DBPoolOptions::default()).await.unwrap();
+            assert_eq!(sources.names(), vec!["order", "user"]);
+            assert_eq!(sources.default, Some("order".to_string()));
+            sources.get("user").unwrap().acquire().await.unwrap().exec("SELECT 1").await.unwrap();
+            assert!(sources.get("none").is_err());
+            assert!(sources.remove("order").is_err());
+            sources.set_default("user").unwrap();
+            let order = sources.remove("order").unwrap();
+            sources.close().await;
+            assert!(sources.get_default().unwrap().acquire().await.is_err());
+            assert!(order.acquire().await.is_ok());
+
+            let config: DataSourcesConfig = serde_json::from_str(r#"{
+                "datasources": {"a": {"url": "sqlite::memory:"}, "b": {"url": "sqlite::memory:"}}
+            }"#).unwrap();
+            assert!(DataSources::from_config(&config, &DBPoolOptions::default()).await.is_err());
+            assert!(serde_json::from_str::<DataSourcesConfig>(r#"{"datasources": {"a": 1}}"#).is_err());
+        });
+    }
+}
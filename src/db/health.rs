@@ -0,0 +1,57 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// the result of the recent pings, see DBPool::health()
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct DBPoolHealth {
+    /// the last keepalive round or health_check() succeed
+    pub healthy: bool,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    /// the round trip of the last succeed ping, in milliseconds
+    pub latency_ms: Option<f64>,
+}
+
+/// the health shared by all clones of a DBPool, written by the keepalive task and health_check()
+#[derive(Debug, Default)]
+pub struct PoolHealth {
+    /// the keepalive task is running
+    pub running: AtomicBool,
+    health: Mutex<DBPoolHealth>,
+}
+
+impl PoolHealth {
+    pub fn record(&self, result: &crate::Result<()>, latency: Duration) {
+        let mut health = self.health.lock().unwrap_or_else(|e| e.into_inner());
+        match result {
+            Ok(_) => {
+                health.healthy = true;
+                health.last_success = Some(Utc::now());
+                health.latency_ms = Some(latency.as_secs_f64() * 1000.0);
+            }
+            Err(e) => {
+                health.healthy = false;
+                health.last_error = Some(e.to_string());
+                health.last_error_at = Some(Utc::now());
+            }
+        }
+    }
+
+    pub fn health(&self) -> DBPoolHealth {
+        self.health.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// mark the task running, false if it is running already
+    pub fn start(&self) -> bool {
+        !self.running.swap(true, Ordering::AcqRel)
+    }
+
+    pub fn stop(&self) {
+        self.running.store(false, Ordering::Release);
+    }
+}
@@ -1,18 +1,48 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
 use std::time::Duration;
 
 use chrono::NaiveDateTime;
+use futures_core::future::BoxFuture;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
 pub use db_adapter::{
-    DBConnectOption, DBExecResult, DBPool, DBPoolConn, DBQuery, DBTx,
+    DBConnection, DBConnectOption, DBExecResult, DBPool, DBPoolConn, DBQuery, DBTx, SqlxPool,
 };
 use py_sql::StringConvert;
 use crate::convert::StmtConvert;
 
+pub use breaker::{BreakerEvent, BreakerState, CircuitBreakerOptions};
+pub use config::{DatabaseConfig, PoolConfig, TlsConfig, TlsMode};
+pub use credential::{Credential, CredentialProvider, FileCredentialProvider};
+pub use datasources::{DataSources, DataSourcesConfig};
+pub use health::DBPoolHealth;
+pub use routing::{DBRoutingPool, LoadBalance, Replica};
+pub use shutdown::{LeaseInfo, ShutdownReport};
+pub use stats::DBPoolStats;
+
+pub mod breaker;
+pub mod config;
+pub mod credential;
+pub mod datasources;
+pub mod health;
 pub mod db_adapter;
+pub mod routing;
+pub mod shutdown;
+pub mod stats;
 
-#[derive(Debug, Clone, Copy)]
+/// run on every new physical conn, e.g. `SET time_zone` or sqlite PRAGMAs
+pub type AfterConnect = Arc<dyn for<'c> Fn(DBConnection<'c>) -> BoxFuture<'c, crate::Result<()>> + Send + Sync>;
+/// run before an idle conn is handed out, Ok(false) or Err close the conn
+pub type BeforeAcquire = Arc<dyn for<'c> Fn(DBConnection<'c>) -> BoxFuture<'c, crate::Result<bool>> + Send + Sync>;
+/// run when a conn is given back to the pool, false close the conn.
+/// sqlx calls it synchronously, so it can not run any sql on the conn(e.g. a session reset),
+/// do the reset in before_acquire or after_connect instead
+pub type AfterRelease = Arc<dyn for<'c> Fn(DBConnection<'c>) -> bool + Send + Sync>;
+
+/// not Copy since the hooks are Arc closures, clone it instead
+#[derive(Clone)]
 pub struct DBPoolOptions {
     pub max_connections: u32,
     pub min_connections: u32,
@@ -20,6 +50,42 @@ pub struct DBPoolOptions {
     pub max_lifetime: Option<Duration>,
     pub idle_timeout: Option<Duration>,
     pub test_before_acquire: bool,
+    /// how the bound args are written to errors
+    pub redact: ArgRedact,
+    pub after_connect: Option<AfterConnect>,
+    pub before_acquire: Option<BeforeAcquire>,
+    pub after_release: Option<AfterRelease>,
+    /// retry acquire() on the errors of retry_if(default Error::is_transient), max_attempts is 1(never retry) by default
+    pub retry: RetryPolicy,
+    /// retry DBPoolConn::fetch/fetch_parperd with the retry policy too, with a new conn if the conn is lost.
+    /// only for idempotent queries, the conns of DBTx never retry
+    pub retry_fetch: bool,
+    /// every interval ping the idle conns in background, replace the dead ones and keep min_connections open.
+    /// None(default) disable it, see DBPool::health()
+    pub keepalive: Option<Duration>,
+    /// fail acquire fast with Error::CircuitOpen after consecutive connection failures, None(default) disable it
+    pub circuit_breaker: Option<CircuitBreakerOptions>,
+}
+
+impl Debug for DBPoolOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DBPoolOptions")
+            .field("max_connections", &self.max_connections)
+            .field("min_connections", &self.min_connections)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("max_lifetime", &self.max_lifetime)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("test_before_acquire", &self.test_before_acquire)
+            .field("redact", &self.redact)
+            .field("after_connect", &self.after_connect.is_some())
+            .field("before_acquire", &self.before_acquire.is_some())
+            .field("after_release", &self.after_release.is_some())
+            .field("retry", &self.retry)
+            .field("retry_fetch", &self.retry_fetch)
+            .field("keepalive", &self.keepalive)
+            .field("circuit_breaker", &self.circuit_breaker)
+            .finish()
+    }
 }
 
 impl Default for DBPoolOptions {
@@ -39,6 +105,19 @@ impl Default for DBPoolOptions {
             idle_timeout: None,
             // If true, test the health of a connection on acquire
             test_before_acquire: true,
+            // strings may be passwords or tokens
+            redact: ArgRedact::Strings,
+            after_connect: None,
+            before_acquire: None,
+            after_release: None,
+            retry: RetryPolicy {
+                max_attempts: 1,
+                retry_if: crate::Error::is_transient,
+                ..RetryPolicy::default()
+            },
+            retry_fetch: false,
+            keepalive: None,
+            circuit_breaker: None,
         }
     }
 }
@@ -47,6 +126,286 @@ impl DBPoolOptions {
     pub fn new() -> Self {
         DBPoolOptions::default()
     }
+
+    pub fn after_connect<F>(mut self, f: F) -> Self
+        where
+            F: for<'c> Fn(DBConnection<'c>) -> BoxFuture<'c, crate::Result<()>> + Send + Sync + 'static,
+    {
+        self.after_connect = Some(Arc::new(f));
+        self
+    }
+
+    pub fn before_acquire<F>(mut self, f: F) -> Self
+        where
+            F: for<'c> Fn(DBConnection<'c>) -> BoxFuture<'c, crate::Result<bool>> + Send + Sync + 'static,
+    {
+        self.before_acquire = Some(Arc::new(f));
+        self
+    }
+
+    pub fn after_release<F>(mut self, f: F) -> Self
+        where
+            F: for<'c> Fn(DBConnection<'c>) -> bool + Send + Sync + 'static,
+    {
+        self.after_release = Some(Arc::new(f));
+        self
+    }
+}
+
+/// redaction policy of the bound args attached to query errors
+#[derive(Debug, Clone, Copy)]
+pub enum ArgRedact {
+    /// write the values as is
+    Off,
+    /// write strings as "***", numbers, bool and null as is
+    Strings,
+    /// write every value as "***"
+    All,
+    /// render the values with a custom fn
+    Custom(fn(&serde_json::Value) -> String),
+}
+
+impl ArgRedact {
+    pub fn render(&self, arg: &serde_json::Value) -> String {
+        match self {
+            ArgRedact::Off => arg.to_string(),
+            ArgRedact::Strings => match arg {
+                serde_json::Value::Null | serde_json::Value::Bool(_) | serde_json::Value::Number(_) => arg.to_string(),
+                _ => "***".to_string(),
+            },
+            ArgRedact::All => "***".to_string(),
+            ArgRedact::Custom(f) => f(arg),
+        }
+    }
+}
+
+/// retry policy of DBPool::transaction() and DBPoolOptions::retry
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// run the closure at most max_attempts times, 1 means never retry
+    pub max_attempts: u32,
+    /// sleep before the first retry, doubled every retry
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    /// sleep a random duration between backoff / 2 and backoff, so the retries of many clients spread out
+    pub jitter: bool,
+    /// is the error worth a retry, is_serialization_failure by default.
+    /// Error::is_retryable also retry lock timeouts and PoolTimeout, opt in with retry_if()
+    pub retry_if: fn(&crate::Error) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+            jitter: true,
+            retry_if: is_serialization_failure,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        RetryPolicy::default()
+    }
+
+    /// never retry
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// e.g. RetryPolicy::new().retry_if(Error::is_retryable)
+    pub fn retry_if(mut self, f: fn(&crate::Error) -> bool) -> Self {
+        self.retry_if = f;
+        self
+    }
+
+    /// the backoff to sleep before the retry after a fail attempt(start from 1)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let mut backoff = self.backoff;
+        for _ in 1..attempt {
+            backoff = backoff.checked_mul(2).unwrap_or(self.max_backoff);
+            if backoff >= self.max_backoff {
+                return self.max_backoff;
+            }
+        }
+        backoff.min(self.max_backoff)
+    }
+
+    /// the backoff() with jitter, the time to sleep before the retry
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self.backoff(attempt);
+        if !self.jitter {
+            return backoff;
+        }
+        let half = backoff / 2;
+        half + Duration::from_nanos(random_u64() % (half.as_nanos() as u64 + 1))
+    }
+}
+
+/// the default retry_if: serialization failure or deadlock,
+/// e.g. pg 40001/40P01 and mysql 1213
+pub fn is_serialization_failure(e: &crate::Error) -> bool {
+    matches!(e, crate::Error::SerializationFailure(_) | crate::Error::Deadlock(_))
+}
+
+/// transaction isolation level
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum IsolationLevel {
+    ReadUncommitted,
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
+    /// mssql only
+    Snapshot,
+}
+
+impl IsolationLevel {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            IsolationLevel::ReadUncommitted => "READ UNCOMMITTED",
+            IsolationLevel::ReadCommitted => "READ COMMITTED",
+            IsolationLevel::RepeatableRead => "REPEATABLE READ",
+            IsolationLevel::Serializable => "SERIALIZABLE",
+            IsolationLevel::Snapshot => "SNAPSHOT",
+        }
+    }
+}
+
+/// transaction access mode
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum AccessMode {
+    ReadWrite,
+    ReadOnly,
+}
+
+/// sqlite lock behavior of BEGIN
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SqliteBehavior {
+    Deferred,
+    Immediate,
+    Exclusive,
+}
+
+/// options of begin_with(), the default is a plain BEGIN
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct TxOptions {
+    pub isolation_level: Option<IsolationLevel>,
+    pub access_mode: Option<AccessMode>,
+    /// postgres only, only takes effect with SERIALIZABLE READ ONLY
+    pub deferrable: bool,
+    /// sqlite only
+    pub sqlite_behavior: Option<SqliteBehavior>,
+}
+
+impl TxOptions {
+    pub fn new() -> Self {
+        TxOptions::default()
+    }
+
+    pub fn isolation_level(mut self, level: IsolationLevel) -> Self {
+        self.isolation_level = Some(level);
+        self
+    }
+
+    pub fn read_only(mut self) -> Self {
+        self.access_mode = Some(AccessMode::ReadOnly);
+        self
+    }
+
+    pub fn read_write(mut self) -> Self {
+        self.access_mode = Some(AccessMode::ReadWrite);
+        self
+    }
+
+    pub fn deferrable(mut self) -> Self {
+        self.deferrable = true;
+        self
+    }
+
+    pub fn sqlite_behavior(mut self, behavior: SqliteBehavior) -> Self {
+        self.sqlite_behavior = Some(behavior);
+        self
+    }
+}
+
+const MSSQL_SAVE_XACT_ABORT: &str = "DECLARE @xact_abort INT = @@OPTIONS & 16384; \
+EXEC sp_set_session_context N'rbatis.xact_abort', @xact_abort; SET XACT_ABORT ON";
+const MSSQL_RESTORE_XACT_ABORT: &str = "IF CAST(SESSION_CONTEXT(N'rbatis.xact_abort') AS INT) = 0 SET XACT_ABORT OFF";
+//sys.dm_exec_sessions.transaction_isolation_level: 1 READ UNCOMMITTED, 2 READ COMMITTED, 3 REPEATABLE READ, 4 SERIALIZABLE, 5 SNAPSHOT
+const MSSQL_SAVE_ISOLATION_LEVEL: &str = "DECLARE @level SMALLINT = \
+(SELECT transaction_isolation_level FROM sys.dm_exec_sessions WHERE session_id = @@SPID); \
+EXEC sp_set_session_context N'rbatis.isolation_level', @level";
+const MSSQL_RESTORE_ISOLATION_LEVEL: &str = "DECLARE @level SMALLINT = CAST(SESSION_CONTEXT(N'rbatis.isolation_level') AS SMALLINT); \
+IF @level = 1 SET TRANSACTION ISOLATION LEVEL READ UNCOMMITTED \
+ELSE IF @level = 3 SET TRANSACTION ISOLATION LEVEL REPEATABLE READ \
+ELSE IF @level = 4 SET TRANSACTION ISOLATION LEVEL SERIALIZABLE \
+ELSE IF @level = 5 SET TRANSACTION ISOLATION LEVEL SNAPSHOT \
+ELSE SET TRANSACTION ISOLATION LEVEL READ COMMITTED";
+
+/// statements of DriverType::begin_sql(), run in order: before, begin, after
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct BeginSql {
+    pub before: Vec<String>,
+    /// None means the native BEGIN of the sqlx driver
+    pub begin: Option<String>,
+    pub after: Vec<String>,
+    /// run after commit/rollback, restore the session settings changed by before to their previous values
+    pub end: Vec<String>,
+}
+
+/// the order DBPool try the hosts of a multi-host url, url param load_balance_hosts=disable|random
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum HostOrder {
+    InOrder,
+    Random,
+}
+
+impl HostOrder {
+    pub fn parse(value: &str) -> crate::Result<Self> {
+        match value {
+            "disable" => Ok(HostOrder::InOrder),
+            "random" => Ok(HostOrder::Random),
+            _ => Err(crate::Error::from(format!("[rbatis-core] invalid load_balance_hosts: \"{}\"", value))),
+        }
+    }
+}
+
+/// the server a conn may use, url param target_session_attrs=any|read-write|read-only
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TargetSessionAttrs {
+    Any,
+    ReadWrite,
+    ReadOnly,
+}
+
+impl TargetSessionAttrs {
+    pub fn parse(value: &str) -> crate::Result<Self> {
+        match value {
+            "any" => Ok(TargetSessionAttrs::Any),
+            "read-write" => Ok(TargetSessionAttrs::ReadWrite),
+            "read-only" => Ok(TargetSessionAttrs::ReadOnly),
+            _ => Err(crate::Error::from(format!("[rbatis-core] invalid target_session_attrs: \"{}\"", value))),
+        }
+    }
+}
+
+/// a random u64 without a rand dependency, for host order and backoff jitter
+pub(crate) fn random_u64() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|x| x.as_nanos())
+            .unwrap_or_default(),
+    );
+    hasher.finish()
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
@@ -69,5 +428,228 @@ impl DriverType {
             }
         }
     }
+
+    /// the statements that begin a transaction with the options
+    pub fn begin_sql(&self, opt: &TxOptions) -> crate::Result<BeginSql> {
+        let unsupported = |what: &str| {
+            Err(crate::Error::from(format!("[rbatis-core] {:?} not support tx option: {}", self, what)))
+        };
+        if opt.deferrable && *self != DriverType::Postgres {
+            return unsupported("deferrable");
+        }
+        if opt.sqlite_behavior.is_some() && *self != DriverType::Sqlite {
+            return unsupported("sqlite_behavior");
+        }
+        if opt.isolation_level == Some(IsolationLevel::Snapshot) && *self != DriverType::Mssql {
+            return unsupported("isolation_level SNAPSHOT");
+        }
+        let access_mode = opt.access_mode.map(|m| match m {
+            AccessMode::ReadWrite => "READ WRITE",
+            AccessMode::ReadOnly => "READ ONLY",
+        });
+        let mut sql = BeginSql::default();
+        match self {
+            DriverType::None => {
+                return Err(crate::Error::from("un init DBPool!"));
+            }
+            DriverType::Mysql => {
+                //SET TRANSACTION only applies to the next transaction
+                let mut characteristics = vec![];
+                if let Some(level) = opt.isolation_level {
+                    characteristics.push(format!("ISOLATION LEVEL {}", level.as_sql()));
+                }
+                if let Some(mode) = access_mode {
+                    characteristics.push(mode.to_string());
+                }
+                if !characteristics.is_empty() {
+                    sql.before.push(format!("SET TRANSACTION {}", characteristics.join(", ")));
+                }
+            }
+            DriverType::Postgres => {
+                //SET TRANSACTION must be the first statement of the transaction
+                let mut characteristics = vec![];
+                if let Some(level) = opt.isolation_level {
+                    characteristics.push(format!("ISOLATION LEVEL {}", level.as_sql()));
+                }
+                if let Some(mode) = access_mode {
+                    characteristics.push(mode.to_string());
+                }
+                if opt.deferrable {
+                    characteristics.push("DEFERRABLE".to_string());
+                }
+                if !characteristics.is_empty() {
+                    sql.after.push(format!("SET TRANSACTION {}", characteristics.join(" ")));
+                }
+            }
+            DriverType::Sqlite => {
+                match opt.isolation_level {
+                    None | Some(IsolationLevel::Serializable) => {}
+                    Some(_) => {
+                        return unsupported("isolation_level other than SERIALIZABLE");
+                    }
+                }
+                if access_mode.is_some() {
+                    return unsupported("access_mode");
+                }
+                sql.begin = match opt.sqlite_behavior {
+                    None | Some(SqliteBehavior::Deferred) => None,
+                    Some(SqliteBehavior::Immediate) => Some("BEGIN IMMEDIATE".to_string()),
+                    Some(SqliteBehavior::Exclusive) => Some("BEGIN EXCLUSIVE".to_string()),
+                };
+            }
+            DriverType::Mssql => {
+                if access_mode.is_some() {
+                    return unsupported("access_mode");
+                }
+                //any runtime error aborts the whole transaction, like the other databases.
+                //the session keeps SET options, so the previous values are saved in the session context and restored
+                sql.before.push(MSSQL_SAVE_XACT_ABORT.to_string());
+                sql.end.push(MSSQL_RESTORE_XACT_ABORT.to_string());
+                if let Some(level) = opt.isolation_level {
+                    sql.before.push(format!("{}; SET TRANSACTION ISOLATION LEVEL {}", MSSQL_SAVE_ISOLATION_LEVEL, level.as_sql()));
+                    sql.end.push(MSSQL_RESTORE_ISOLATION_LEVEL.to_string());
+                }
+            }
+        }
+        Ok(sql)
+    }
+
+    /// the statement to create a savepoint inside an open transaction
+    pub fn savepoint_sql(&self, name: &str) -> crate::Result<String> {
+        check_savepoint_name(name)?;
+        match self {
+            DriverType::Mssql => Ok(format!("SAVE TRANSACTION {}", name)),
+            DriverType::None => Err(crate::Error::from("un init DBPool!")),
+            _ => Ok(format!("SAVEPOINT {}", name)),
+        }
+    }
+
+    /// the statement to roll back to a savepoint, the savepoint itself is kept
+    pub fn rollback_to_sql(&self, name: &str) -> crate::Result<String> {
+        check_savepoint_name(name)?;
+        match self {
+            DriverType::Mssql => Ok(format!("ROLLBACK TRANSACTION {}", name)),
+            DriverType::None => Err(crate::Error::from("un init DBPool!")),
+            _ => Ok(format!("ROLLBACK TO SAVEPOINT {}", name)),
+        }
+    }
+
+    /// the statement to release a savepoint,
+    /// None for mssql which has no release and keeps savepoints until the tx ends
+    pub fn release_sql(&self, name: &str) -> crate::Result<Option<String>> {
+        check_savepoint_name(name)?;
+        match self {
+            DriverType::Mssql => Ok(None),
+            DriverType::None => Err(crate::Error::from("un init DBPool!")),
+            _ => Ok(Some(format!("RELEASE SAVEPOINT {}", name))),
+        }
+    }
+
+    /// the query of one row with a read_only column, true if the server can not write
+    pub fn read_only_sql(&self) -> crate::Result<&'static str> {
+        match self {
+            DriverType::Mysql => Ok("SELECT @@global.read_only AS read_only"),
+            DriverType::Postgres => Ok("SELECT pg_is_in_recovery() OR current_setting('transaction_read_only') = 'on' AS read_only"),
+            DriverType::Mssql => Ok("SELECT CAST(CASE WHEN DATABASEPROPERTYEX(DB_NAME(), 'Updateability') = 'READ_ONLY' THEN 1 ELSE 0 END AS BIT) AS read_only"),
+            DriverType::Sqlite => Ok("SELECT 0 AS read_only"),
+            DriverType::None => Err(crate::Error::from("un init DBPool!")),
+        }
+    }
+
+    /// the statement setting a session variable, the value is spliced as is
+    pub fn session_sql(&self, name: &str, value: &str) -> crate::Result<String> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+            return Err(crate::Error::from(format!("[rbatis-core] invalid session setting name: \"{}\"", name)));
+        }
+        match self {
+            DriverType::Mysql | DriverType::Postgres => Ok(format!("SET {} = {}", name, value)),
+            DriverType::Mssql => Ok(format!("SET {} {}", name, value)),
+            DriverType::Sqlite => Ok(format!("PRAGMA {} = {}", name, value)),
+            DriverType::None => Err(crate::Error::from("un init DBPool!")),
+        }
+    }
 }
 
+/// savepoint names are spliced into sql, so only plain identifiers are allowed
+fn check_savepoint_name(name: &str) -> crate::Result<()> {
+    let mut chars = name.chars();
+    let valid = match chars.next() {
+        Some(c) => (c.is_ascii_alphabetic() || c == '_') && chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        None => false,
+    };
+    if !valid {
+        return Err(crate::Error::from(format!("[rbatis-core] invalid savepoint name: \"{}\"", name)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::time::Duration;
+
+    use crate::db::{BeginSql, DriverType, IsolationLevel, RetryPolicy, SqliteBehavior, TxOptions};
+    use crate::db::{MSSQL_RESTORE_ISOLATION_LEVEL, MSSQL_RESTORE_XACT_ABORT, MSSQL_SAVE_XACT_ABORT};
+    use crate::Error;
+
+    /// poll f until it is true, panic after 5s
+    pub(crate) async fn wait_until<F: Fn() -> bool>(f: F) {
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while !f() {
+            assert!(std::time::Instant::now() < deadline, "wait_until timeout");
+            crate::runtime::task::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[test]
+    fn test_savepoint_sql() {
+        assert_eq!(DriverType::Postgres.savepoint_sql("sp_1").unwrap(), "SAVEPOINT sp_1");
+        assert_eq!(DriverType::Mssql.savepoint_sql("sp_1").unwrap(), "SAVE TRANSACTION sp_1");
+        assert_eq!(DriverType::Mysql.rollback_to_sql("sp_1").unwrap(), "ROLLBACK TO SAVEPOINT sp_1");
+        assert_eq!(DriverType::Mssql.rollback_to_sql("sp_1").unwrap(), "ROLLBACK TRANSACTION sp_1");
+        assert_eq!(DriverType::Mssql.release_sql("sp_1").unwrap(), None);
+        assert!(DriverType::Sqlite.savepoint_sql("sp; DROP TABLE t").is_err());
+    }
+
+    #[test]
+    fn test_begin_sql() {
+        let opt = TxOptions::new().isolation_level(IsolationLevel::Serializable).read_only();
+        let pg = DriverType::Postgres.begin_sql(&opt.deferrable()).unwrap();
+        assert_eq!(pg.after, vec!["SET TRANSACTION ISOLATION LEVEL SERIALIZABLE READ ONLY DEFERRABLE"]);
+        assert_eq!(pg.begin, None);
+        let mysql = DriverType::Mysql.begin_sql(&opt).unwrap();
+        assert_eq!(mysql.before, vec!["SET TRANSACTION ISOLATION LEVEL SERIALIZABLE, READ ONLY"]);
+        assert!(DriverType::Mssql.begin_sql(&opt).is_err());
+        let mssql = DriverType::Mssql.begin_sql(&TxOptions::new().isolation_level(IsolationLevel::Snapshot)).unwrap();
+        assert_eq!(mssql.before[0], MSSQL_SAVE_XACT_ABORT);
+        assert!(mssql.before[1].ends_with("; SET TRANSACTION ISOLATION LEVEL SNAPSHOT"));
+        assert_eq!(mssql.end, vec![MSSQL_RESTORE_XACT_ABORT, MSSQL_RESTORE_ISOLATION_LEVEL]);
+        //a plain begin() leaves the isolation level of the session alone
+        assert_eq!(DriverType::Mssql.begin_sql(&TxOptions::new()).unwrap().end, vec![MSSQL_RESTORE_XACT_ABORT]);
+        assert!(pg.end.is_empty() && mysql.end.is_empty());
+        let sqlite = DriverType::Sqlite.begin_sql(&TxOptions::new().sqlite_behavior(SqliteBehavior::Immediate)).unwrap();
+        assert_eq!(sqlite.begin, Some("BEGIN IMMEDIATE".to_string()));
+        assert_eq!(DriverType::Sqlite.begin_sql(&TxOptions::new()).unwrap(), BeginSql::default());
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let policy = RetryPolicy {
+            backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(10));
+        assert_eq!(policy.backoff(2), Duration::from_millis(20));
+        assert_eq!(policy.backoff(3), Duration::from_millis(40));
+        assert_eq!(policy.backoff(4), Duration::from_millis(50));
+        for attempt in 1..5 {
+            let delay = policy.delay(attempt);
+            assert!(delay >= policy.backoff(attempt) / 2 && delay <= policy.backoff(attempt));
+        }
+        assert_eq!(RetryPolicy { jitter: false, ..policy }.delay(2), Duration::from_millis(20));
+        assert!((policy.retry_if)(&Error::Deadlock(Box::default())));
+        assert!(!(policy.retry_if)(&Error::from("deadlock detected")));
+        assert!(!(policy.retry_if)(&Error::PoolTimeout));
+        assert!((RetryPolicy::new().retry_if(Error::is_retryable).retry_if)(&Error::PoolTimeout));
+    }
+}
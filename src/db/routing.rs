@@ -0,0 +1,357 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use futures_core::future::BoxFuture;
+use serde::de::DeserializeOwned;
+
+use crate::db::{DBExecResult, DBPool, DBPoolConn, DBQuery, DBTx, RetryPolicy, TxOptions};
+use crate::Error;
+
+/// how DBRoutingPool picks a replica for reads
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum LoadBalance {
+    RoundRobin,
+    /// the replica with the least in use conns and waiters
+    LeastBusy,
+}
+
+/// a read replica of DBRoutingPool
+#[derive(Debug, Clone)]
+pub struct Replica {
+    pub pool: DBPool,
+    /// false after the replica fail a health check or lost the conn, reads skip it
+    pub healthy: Arc<AtomicBool>,
+}
+
+impl Replica {
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+}
+
+/// read/write splitting pool: fetch go to the replicas, exec and tx go to the primary.
+/// a read failing to reach its replica is retried on the other healthy replicas, then on the primary
+#[derive(Debug, Clone)]
+pub struct DBRoutingPool {
+    pub primary: DBPool,
+    pub replicas: Vec<Replica>,
+    pub balance: LoadBalance,
+    /// send reads to the primary too, see with_primary()
+    pub force_primary: bool,
+    next: Arc<AtomicUsize>,
+}
+
+impl DBRoutingPool {
+    /// the replicas must use the same driver as the primary
+    pub fn new(primary: DBPool, replicas: Vec<DBPool>) -> crate::Result<Self> {
+        if replicas.iter().any(|x| x.driver_type != primary.driver_type) {
+            return Err(Error::from("[rbatis-core] the driver type of replica and primary not the same!"));
+        }
+        Ok(Self {
+            primary,
+            replicas: replicas
+                .into_iter()
+                .map(|pool| Replica {
+                    pool,
+                    healthy: Arc::new(AtomicBool::new(true)),
+                })
+                .collect(),
+            balance: LoadBalance::RoundRobin,
+            force_primary: false,
+            next: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn balance(mut self, balance: LoadBalance) -> Self {
+        self.balance = balance;
+        self
+    }
+
+    /// a clone sending reads to the primary, for read-your-writes
+    pub fn with_primary(&self) -> Self {
+        let mut pool = self.clone();
+        pool.force_primary = true;
+        pool
+    }
+
+    /// the pool a read should go to
+    pub fn reader(&self) -> &DBPool {
+        if self.force_primary {
+            return &self.primary;
+        }
+        self.pick_replica()
+            .map(|x| &x.pool)
+            .unwrap_or(&self.primary)
+    }
+
+    fn pick_replica(&self) -> Option<&Replica> {
+        let healthy: Vec<&Replica> = self.replicas.iter().filter(|x| x.is_healthy()).collect();
+        if healthy.is_empty() {
+            return None;
+        }
+        match self.balance {
+            LoadBalance::RoundRobin => {
+                let index = self.next.fetch_add(1, Ordering::Relaxed) % healthy.len();
+                Some(healthy[index])
+            }
+            LoadBalance::LeastBusy => healthy.into_iter().min_by_key(|x| {
+                let stats = x.pool.stats();
+                stats.in_use as u64 + stats.waiters
+            }),
+        }
+    }
+
+    /// the pools a read try in order: the picked replica, the other healthy replicas, the primary
+    fn readers(&self) -> Vec<&DBPool> {
+        if self.force_primary {
+            return vec![&self.primary];
+        }
+        let mut readers: Vec<&DBPool> = vec![];
+        if let Some(first) = self.pick_replica() {
+            readers.push(&first.pool);
+            for replica in &self.replicas {
+                if replica.is_healthy() && !std::ptr::eq(&replica.pool, &first.pool) {
+                    readers.push(&replica.pool);
+                }
+            }
+        }
+        readers.push(&self.primary);
+        readers
+    }
+
+    /// eject the replica of the pool if the error means the server is gone.
+    /// true if the read should go on to the next pool
+    fn check_replica(&self, pool: &DBPool, e: &Error) -> bool {
+        if std::ptr::eq(pool, &self.primary) {
+            return false;
+        }
+        if e.is_connection_lost() {
+            for replica in &self.replicas {
+                if std::ptr::eq(&replica.pool, pool) {
+                    log::warn!("[rbatis-core] replica lost the conn: {}, eject it", e);
+                    replica.healthy.store(false, Ordering::Relaxed);
+                }
+            }
+            return true;
+        }
+        matches!(e, Error::PoolTimeout | Error::PoolClosed | Error::CircuitOpen)
+    }
+
+    /// acquire a conn for reads
+    pub async fn acquire_read(&self) -> crate::Result<DBPoolConn> {
+        let mut last = Err(Error::from("[rbatis-core] no pool to read!"));
+        for pool in self.readers() {
+            last = pool.acquire().await;
+            match &last {
+                Err(e) if self.check_replica(pool, e) => {
+                    log::warn!("[rbatis-core] replica read fail: {}, try the next pool", e);
+                }
+                _ => break,
+            }
+        }
+        last
+    }
+
+    /// acquire a conn of the primary
+    pub async fn acquire(&self) -> crate::Result<DBPoolConn> {
+        self.primary.acquire().await
+    }
+
+    pub fn make_query<'s>(&self, sql: &'s str) -> crate::Result<DBQuery<'s>> {
+        self.primary.make_query(sql)
+    }
+
+    pub async fn fetch<T>(&self, sql: &str) -> crate::Result<(T, usize)>
+        where
+            T: DeserializeOwned,
+    {
+        let mut last = Err(Error::from("[rbatis-core] no pool to read!"));
+        for pool in self.readers() {
+            last = async { pool.acquire().await?.fetch(sql).await }.await;
+            match &last {
+                Err(e) if self.check_replica(pool, e) => {
+                    log::warn!("[rbatis-core] replica read fail: {}, try the next pool", e);
+                }
+                _ => break,
+            }
+        }
+        last
+    }
+
+    pub async fn fetch_parperd<T>(&self, sql: DBQuery<'_>) -> crate::Result<(T, usize)>
+        where
+            T: DeserializeOwned,
+    {
+        let readers = self.readers();
+        let mut sql = Some(sql);
+        let mut last = Err(Error::from("[rbatis-core] no pool to read!"));
+        for (index, pool) in readers.iter().enumerate() {
+            let query = match sql.take() {
+                Some(query) => query,
+                None => break,
+            };
+            //keep a copy of the query only if there is a next pool to fall back to
+            if index + 1 < readers.len() {
+                sql = Some(query.rebuild()?);
+            }
+            last = async { pool.acquire().await?.fetch_parperd(query).await }.await;
+            match &last {
+                Err(e) if self.check_replica(pool, e) => {
+                    log::warn!("[rbatis-core] replica read fail: {}, try the next pool", e);
+                }
+                _ => break,
+            }
+        }
+        last
+    }
+
+    pub async fn exec(&self, sql: &str) -> crate::Result<DBExecResult> {
+        self.primary.acquire().await?.exec(sql).await
+    }
+
+    pub async fn exec_prepare(&self, sql: DBQuery<'_>) -> crate::Result<DBExecResult> {
+        self.primary.acquire().await?.exec_prepare(sql).await
+    }
+
+    pub async fn begin(&self) -> crate::Result<DBTx> {
+        self.primary.begin().await
+    }
+
+    pub async fn begin_with(&self, opt: &TxOptions) -> crate::Result<DBTx> {
+        self.primary.begin_with(opt).await
+    }
+
+    /// DBPool::transaction() on the primary
+    pub async fn transaction<R, F>(&self, f: F) -> crate::Result<R>
+        where
+            F: for<'c> FnMut(&'c mut DBTx) -> BoxFuture<'c, crate::Result<R>>,
+    {
+        self.primary.transaction(f).await
+    }
+
+    /// DBPool::transaction_with() on the primary
+    pub async fn transaction_with<R, F>(&self, opt: &TxOptions, policy: &RetryPolicy, f: F) -> crate::Result<R>
+        where
+            F: for<'c> FnMut(&'c mut DBTx) -> BoxFuture<'c, crate::Result<R>>,
+    {
+        self.primary.transaction_with(opt, policy, f).await
+    }
+
+    /// ping every replica, eject the failed ones and bring back the recovered ones.
+    /// return the count of healthy replicas
+    pub async fn health_check(&self) -> usize {
+        let mut healthy = 0;
+        for replica in &self.replicas {
+            let result = async { replica.pool.acquire().await?.ping().await }.await;
+            match result {
+                Ok(_) => {
+                    if !replica.healthy.swap(true, Ordering::Relaxed) {
+                        log::info!("[rbatis-core] replica recovered");
+                    }
+                    healthy += 1;
+                }
+                Err(e) => {
+                    if replica.healthy.swap(false, Ordering::Relaxed) {
+                        log::warn!("[rbatis-core] replica health check fail: {}, eject it", e);
+                    }
+                }
+            }
+        }
+        healthy
+    }
+
+    /// run health_check() every interval in a background task, so the ejected replicas come back once they recover.
+    /// the task stops when the last clone of the DBRoutingPool is dropped or the primary is closed
+    pub fn spawn_health_check(&self, interval: Duration) {
+        let pool = self.clone();
+        crate::runtime::task::spawn(async move {
+            loop {
+                crate::runtime::task::sleep(interval).await;
+                //the task hold the last clone
+                if Arc::strong_count(&pool.next) == 1 || pool.primary.leases.is_closing() {
+                    break;
+                }
+                pool.health_check().await;
+            }
+        });
+    }
+
+    pub async fn close(&self) {
+        self.primary.close().await;
+        for replica in &self.replicas {
+            replica.pool.close().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use crate::db::DBPool;
+    use crate::db::test::wait_until;
+    use crate::db::routing::{DBRoutingPool, LoadBalance};
+
+    #[test]
+    fn test_routing() {
+        crate::runtime::task::block_on(async {
+            let primary = DBPool::new("sqlite::memory:").await.unwrap();
+            let replica_a = DBPool::new("sqlite::memory:").await.unwrap();
+            let replica_b = DBPool::new("sqlite::memory:").await.unwrap();
+            let pool = DBRoutingPool::new(primary, vec![replica_a, replica_b]).unwrap();
+            assert!(std::ptr::eq(pool.reader(), &pool.replicas[0].pool));
+            assert!(std::ptr::eq(pool.reader(), &pool.replicas[1].pool));
+            let force = pool.with_primary();
+            assert!(std::ptr::eq(force.reader(), &force.primary));
+
+            pool.replicas[0].healthy.store(false, Ordering::Relaxed);
+            pool.replicas[1].healthy.store(false, Ordering::Relaxed);
+            assert!(std::ptr::eq(pool.reader(), &pool.primary));
+            assert_eq!(pool.health_check().await, 2);
+
+            pool.replicas[1].pool.close().await;
+            assert_eq!(pool.health_check().await, 1);
+            let pool = pool.balance(LoadBalance::LeastBusy);
+            assert!(std::ptr::eq(pool.reader(), &pool.replicas[0].pool));
+        });
+    }
+
+    #[test]
+    fn test_routing_fallback() {
+        crate::runtime::task::block_on(async {
+            let primary = DBPool::new("sqlite::memory:").await.unwrap();
+            let replica_a = DBPool::new("sqlite::memory:").await.unwrap();
+            let replica_b = DBPool::new("sqlite::memory:").await.unwrap();
+            let pool = DBRoutingPool::new(primary, vec![replica_a, replica_b]).unwrap();
+            pool.replicas[0].pool.close().await;
+            for _ in 0..2 {
+                let (v, _) = pool.fetch::<serde_json::Value>("select 1 as a").await.unwrap();
+                assert_eq!(v[0]["a"], 1);
+                assert!(pool.acquire_read().await.is_ok());
+            }
+            pool.replicas[1].pool.close().await;
+            let mut query = pool.make_query("select ? as a").unwrap();
+            query.bind_value(&serde_json::json!(2)).unwrap();
+            let (v, _) = pool.fetch_parperd::<serde_json::Value>(query).await.unwrap();
+            assert_eq!(v[0]["a"], 2);
+            //the primary has nothing to fall back to
+            pool.primary.close().await;
+            assert!(pool.fetch::<serde_json::Value>("select 1 as a").await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_spawn_health_check() {
+        crate::runtime::task::block_on(async {
+            let primary = DBPool::new("sqlite::memory:").await.unwrap();
+            let replica = DBPool::new("sqlite::memory:").await.unwrap();
+            let pool = DBRoutingPool::new(primary, vec![replica]).unwrap();
+            pool.replicas[0].healthy.store(false, Ordering::Relaxed);
+            pool.spawn_health_check(Duration::from_millis(10));
+            wait_until(|| pool.replicas[0].is_healthy()).await;
+            pool.close().await;
+        });
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+/// the result of DBPool::shutdown()
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ShutdownReport {
+    /// all conns were given back before the deadline
+    pub drained: bool,
+    /// the conns still checked out at the deadline. their statements fail from then on,
+    /// and a DBTx on them is closed when it is rolled back or dropped, so the server roll back the tx
+    pub terminated: Vec<LeaseInfo>,
+    pub elapsed_ms: f64,
+}
+
+/// a conn checked out of the pool
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LeaseInfo {
+    pub id: u64,
+    /// how long the conn is held
+    pub held_ms: f64,
+    /// a DBTx is open on the conn
+    pub in_tx: bool,
+}
+
+/// the conns handed out by the clones of a DBPool
+#[derive(Debug, Default)]
+pub struct Leases {
+    next_id: AtomicU64,
+    /// set by close() and shutdown(), acquire fail with Error::PoolClosed
+    closing: AtomicBool,
+    /// set at the shutdown deadline, the statements of the leased conns fail with Error::PoolClosed
+    terminated: AtomicBool,
+    open: Mutex<HashMap<u64, (Instant, bool)>>,
+}
+
+impl Leases {
+    pub fn lease(self: &Arc<Self>) -> Lease {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.open
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id, (Instant::now(), false));
+        Lease {
+            id,
+            leases: self.clone(),
+        }
+    }
+
+    pub fn is_closing(&self) -> bool {
+        self.closing.load(Ordering::Acquire)
+    }
+
+    pub fn close(&self) {
+        self.closing.store(true, Ordering::Release);
+    }
+
+    pub fn is_terminated(&self) -> bool {
+        self.terminated.load(Ordering::Acquire)
+    }
+
+    /// fail the statements of the leased conns, return them
+    pub fn terminate(&self) -> Vec<LeaseInfo> {
+        self.terminated.store(true, Ordering::Release);
+        let mut leases: Vec<LeaseInfo> = self
+            .open
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(id, (acquired, in_tx))| LeaseInfo {
+                id: *id,
+                held_ms: acquired.elapsed().as_secs_f64() * 1000.0,
+                in_tx: *in_tx,
+            })
+            .collect();
+        leases.sort_by_key(|x| x.id);
+        leases
+    }
+}
+
+/// a checked out conn, removed from Leases on drop
+#[derive(Debug)]
+pub struct Lease {
+    pub id: u64,
+    leases: Arc<Leases>,
+}
+
+impl Lease {
+    pub fn is_terminated(&self) -> bool {
+        self.leases.is_terminated()
+    }
+
+    pub fn set_in_tx(&self, in_tx: bool) {
+        if let Some(lease) = self.leases.open.lock().unwrap_or_else(|e| e.into_inner()).get_mut(&self.id) {
+            lease.1 = in_tx;
+        }
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.leases.open.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.id);
+    }
+}
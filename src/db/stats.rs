@@ -0,0 +1,172 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// the acquire latency of the last LATENCY_SAMPLES acquires are kept for the percentiles
+const LATENCY_SAMPLES: usize = 1024;
+
+/// a snapshot of the DBPool state, see DBPool::stats()
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct DBPoolStats {
+    /// open connections, idle and in use
+    pub total: u32,
+    pub idle: u32,
+    pub in_use: u32,
+    /// tasks in acquire() that found no idle conn, waiting for one to be given back or opened
+    pub waiters: u64,
+    /// acquire() succeed
+    pub acquires: u64,
+    /// acquire() failed with Error::PoolTimeout
+    pub acquire_timeouts: u64,
+    /// acquire latency percentiles of the recent acquires, in milliseconds
+    pub acquire_p50_ms: f64,
+    pub acquire_p90_ms: f64,
+    pub acquire_p99_ms: f64,
+    pub acquire_max_ms: f64,
+    /// connections opened and closed since the pool is created.
+    /// sqlx closes some conns without a hook(max_lifetime, idle_timeout, a broken conn), they are counted by the next stats()
+    pub opened: u64,
+    pub closed: u64,
+}
+
+/// the counters shared by all clones of a DBPool
+#[derive(Debug, Default)]
+pub struct PoolMetrics {
+    pub waiters: AtomicU64,
+    pub acquires: AtomicU64,
+    pub acquire_timeouts: AtomicU64,
+    pub opened: AtomicU64,
+    pub closed: AtomicU64,
+    latency: Mutex<VecDeque<Duration>>,
+}
+
+impl PoolMetrics {
+    /// count a waiter until the returned guard is dropped
+    pub fn wait(&self) -> WaitGuard<'_> {
+        self.waiters.fetch_add(1, Ordering::Relaxed);
+        WaitGuard { metrics: self }
+    }
+
+    /// count a conn opened by the sqlx pool of live
+    pub fn record_open(&self, live: &AtomicU32) {
+        self.opened.fetch_add(1, Ordering::Relaxed);
+        live.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// count a conn closed by the sqlx pool of live
+    pub fn record_close(&self, live: &AtomicU32) {
+        if live.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| x.checked_sub(1)).is_ok() {
+            self.closed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// count the conns sqlx closed without a hook(max_lifetime, idle_timeout, a closed pool),
+    /// the open conns of a pool are never more than its size
+    pub fn record_closed_to(&self, live: &AtomicU32, size: u32) {
+        if let Ok(prev) = live.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| if x > size { Some(size) } else { None }) {
+            self.closed.fetch_add((prev - size) as u64, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_acquire(&self, latency: Duration) {
+        self.acquires.fetch_add(1, Ordering::Relaxed);
+        let mut samples = self.latency.lock().unwrap_or_else(|e| e.into_inner());
+        if samples.len() == LATENCY_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(latency);
+    }
+
+    /// fill the counters of stats, total and idle are read from the sqlx pools, the replaced ones included
+    pub fn stats(&self, total: u32, idle: u32) -> DBPoolStats {
+        let mut samples: Vec<Duration> = self
+            .latency
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect();
+        samples.sort();
+        DBPoolStats {
+            total,
+            idle,
+            in_use: total.saturating_sub(idle),
+            waiters: self.waiters.load(Ordering::Relaxed),
+            acquires: self.acquires.load(Ordering::Relaxed),
+            acquire_timeouts: self.acquire_timeouts.load(Ordering::Relaxed),
+            acquire_p50_ms: percentile_ms(&samples, 50),
+            acquire_p90_ms: percentile_ms(&samples, 90),
+            acquire_p99_ms: percentile_ms(&samples, 99),
+            acquire_max_ms: percentile_ms(&samples, 100),
+            opened: self.opened.load(Ordering::Relaxed),
+            closed: self.closed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// decrease the waiters on drop, so a cancelled acquire is not counted forever
+pub struct WaitGuard<'a> {
+    metrics: &'a PoolMetrics,
+}
+
+impl Drop for WaitGuard<'_> {
+    fn drop(&mut self) {
+        self.metrics.waiters.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// nearest-rank percentile of the sorted samples
+fn percentile_ms(sorted: &[Duration], p: usize) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (sorted.len() * p).div_ceil(100).max(1);
+    sorted[rank - 1].as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicU32;
+    use std::time::Duration;
+
+    use crate::db::stats::PoolMetrics;
+
+    #[test]
+    fn test_percentile() {
+        let metrics = PoolMetrics::default();
+        for i in 1..=100 {
+            metrics.record_acquire(Duration::from_millis(i));
+        }
+        let stats = metrics.stats(3, 1);
+        assert_eq!(stats.in_use, 2);
+        assert_eq!(stats.acquires, 100);
+        assert_eq!(stats.acquire_p50_ms, 50.0);
+        assert_eq!(stats.acquire_p99_ms, 99.0);
+        assert_eq!(stats.acquire_max_ms, 100.0);
+        {
+            let _wait = metrics.wait();
+            assert_eq!(metrics.stats(0, 0).waiters, 1);
+        }
+        assert_eq!(metrics.stats(0, 0).waiters, 0);
+    }
+
+    #[test]
+    fn test_closed() {
+        let metrics = PoolMetrics::default();
+        let live = AtomicU32::new(0);
+        for _ in 0..3 {
+            metrics.record_open(&live);
+        }
+        metrics.record_close(&live);
+        //a conn being opened is in the size before it is counted open
+        metrics.record_closed_to(&live, 3);
+        assert_eq!(metrics.stats(3, 0).closed, 1);
+        metrics.record_closed_to(&live, 0);
+        metrics.record_close(&live);
+        let stats = metrics.stats(0, 0);
+        assert_eq!((stats.opened, stats.closed), (3, 3));
+    }
+}
@@ -1,4 +1,6 @@
 //! Types and traits for decoding values from the database.
+use std::sync::Arc;
+
 use serde::de::DeserializeOwned;
 
 use crate::Error;
@@ -84,14 +86,30 @@ pub fn json_decode<T: ?Sized>(datas: Vec<serde_json::Value>) -> Result<T, crate:
     if decode_result.is_ok() {
         return Result::Ok(decode_result.unwrap());
     } else {
-        let e = decode_result.err().unwrap().to_string();
+        let e = decode_result.err().unwrap();
         return Result::Err(Error::Deserialize(format!(
             "[rbatis] json_decode fail decode_type:\"{}\",serde_err:\"{}\"",
             type_name, e
-        )));
+        ), Some(Arc::new(e))));
     }
 }
 
+/// decode one row json object to an object,
+/// used by the row stream so a single row never gets wrapped into an array first.
+pub fn json_decode_row<T>(data: serde_json::Value) -> Result<T, crate::Error>
+    where
+        T: DeserializeOwned,
+{
+    let type_name = std::any::type_name::<T>();
+    if type_name == "serde_json::value::Value" {
+        return serde_json::from_value(data).map_err(|e| Error::Deserialize(format!(
+            "[rbatis] json_decode fail decode_type:\"{}\",serde_err:\"{}\"",
+            type_name, e
+        ), Some(Arc::new(e))));
+    }
+    json_decode(vec![data])
+}
+
 fn is_array(type_name: &str) -> bool {
     if type_name.starts_with("[")
         || type_name.starts_with("&[")
@@ -117,7 +135,7 @@ mod test {
 
     use serde_json::json;
 
-    use crate::decode::{is_array, json_decode};
+    use crate::decode::{is_array, json_decode, json_decode_row};
 
     #[test]
     fn test_decode_hashmap() {
@@ -148,4 +166,13 @@ mod test {
         let dq_name = std::any::type_name::<VecDeque<i32>>();
         assert_eq!(is_array(dq_name), true);
     }
+
+    #[test]
+    fn test_decode_row() {
+        let row = json!({"a":"1","b":2});
+        let v: serde_json::Value = json_decode_row(row.clone()).unwrap();
+        assert_eq!(v, row);
+        let b: i64 = json_decode_row(json!({"b":2})).unwrap();
+        assert_eq!(b, 2);
+    }
 }
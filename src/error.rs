@@ -3,23 +3,221 @@ use std::any::type_name;
 use std::error::Error as StdError;
 use std::fmt::{self, Debug, Display};
 use std::io;
+use std::sync::Arc;
 
-use serde::{Deserialize, Deserializer};
-use serde::de::Visitor;
-use serde::ser::{Serialize, Serializer};
-use sqlx_core::error::BoxDynError;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde::ser::Serializer;
+use sqlx_core::error::{BoxDynError, DatabaseError};
+#[cfg(feature = "mssql")]
+use sqlx_core::mssql::MssqlDatabaseError;
+#[cfg(feature = "mysql")]
+use sqlx_core::mysql::MySqlDatabaseError;
+#[cfg(feature = "postgres")]
+use sqlx_core::postgres::PgDatabaseError;
+#[cfg(feature = "sqlite")]
+use sqlx_core::sqlite::SqliteError;
+
+use crate::db::{ArgRedact, DriverType};
 
 /// A specialized `Result` type for rbatis::core.
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// the original error a crate::Error is converted from, returned by source()
+pub type SourceError = Arc<dyn StdError + Send + Sync + 'static>;
+
 /// A generic error that represents all the ways a method can fail inside of rbatis::core.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[non_exhaustive]
 pub enum Error {
     /// Default Error
     E(String),
-    Deserialize(String),
-    Database(String),
+    Deserialize(String, Option<SourceError>),
+    /// database error without a more specific variant
+    Database(Box<DBErrorInfo>),
+    /// io, tls or protocol error talking to the database
+    Connection(String, Option<SourceError>),
+    /// acquire timed out waiting for a conn of the pool
+    PoolTimeout,
+    /// acquire on a closed pool
+    PoolClosed,
+    /// acquire failed fast, the circuit breaker of the pool is open
+    CircuitOpen,
+    UniqueViolation(Box<DBErrorInfo>),
+    ForeignKeyViolation(Box<DBErrorInfo>),
+    Deadlock(Box<DBErrorInfo>),
+    SerializationFailure(Box<DBErrorInfo>),
+    /// decode a column value fail
+    Decode(String, Option<SourceError>),
+    /// error converted from another crate(rexpr, py_sql, io...)
+    Wrap(String, Option<SourceError>),
+}
+
+/// the details of an error returned from the database
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DBErrorInfo {
+    pub driver_type: Option<DriverType>,
+    pub message: String,
+    /// SQLSTATE, postgres and mysql only
+    pub sqlstate: Option<String>,
+    /// mysql error number or sqlite extended result code.
+    /// None for mssql, sqlx 0.5 does not expose the error number of MssqlDatabaseError
+    pub vendor_code: Option<i64>,
+    pub constraint: Option<String>,
+    pub table: Option<String>,
+    pub column: Option<String>,
+    /// the statement that failed
+    #[serde(default)]
+    pub query: Option<QueryInfo>,
+    /// the driver error
+    #[serde(skip)]
+    pub source: Option<SourceError>,
+}
+
+/// the statement an error is returned from, the args are already redacted
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueryInfo {
+    pub driver_type: DriverType,
+    pub sql: String,
+    pub args: Vec<String>,
+}
+
+impl QueryInfo {
+    pub fn new(driver_type: DriverType, sql: &str, args: &[serde_json::Value], redact: &ArgRedact) -> Self {
+        Self {
+            driver_type,
+            sql: sql.to_string(),
+            args: args.iter().map(|x| redact.render(x)).collect(),
+        }
+    }
+}
+
+impl Display for QueryInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sql: {}", self.sql)?;
+        if !self.args.is_empty() {
+            write!(f, ", args: [{}]", self.args.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for DBErrorInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.vendor_code, &self.sqlstate) {
+            (Some(code), Some(state)) => write!(f, "{} ({}): {}", code, state, self.message),
+            (Some(code), None) => write!(f, "{}: {}", code, self.message),
+            (None, Some(state)) => write!(f, "{}: {}", state, self.message),
+            (None, None) => write!(f, "{}", self.message),
+        }?;
+        if let Some(query) = &self.query {
+            write!(f, ", {}", query)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error {
+    /// the database error details, None if the error is not returned from the database
+    pub fn db_info(&self) -> Option<&DBErrorInfo> {
+        match self {
+            Error::Database(info)
+            | Error::UniqueViolation(info)
+            | Error::ForeignKeyViolation(info)
+            | Error::Deadlock(info)
+            | Error::SerializationFailure(info) => Some(info.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// the statement that failed, None if the error is not returned from a query
+    pub fn query(&self) -> Option<&QueryInfo> {
+        self.db_info().and_then(|x| x.query.as_ref())
+    }
+
+    /// attach the failed statement, database errors keep it in db_info().query,
+    /// decode and connection errors append it to the message
+    pub fn with_query(self, query: QueryInfo) -> Error {
+        match self {
+            Error::Database(mut info) => {
+                info.query = Some(query);
+                Error::Database(info)
+            }
+            Error::UniqueViolation(mut info) => {
+                info.query = Some(query);
+                Error::UniqueViolation(info)
+            }
+            Error::ForeignKeyViolation(mut info) => {
+                info.query = Some(query);
+                Error::ForeignKeyViolation(info)
+            }
+            Error::Deadlock(mut info) => {
+                info.query = Some(query);
+                Error::Deadlock(info)
+            }
+            Error::SerializationFailure(mut info) => {
+                info.query = Some(query);
+                Error::SerializationFailure(info)
+            }
+            Error::Deserialize(msg, source) => Error::Deserialize(format!("{}, {}", msg, query), source),
+            Error::Connection(msg, source) => Error::Connection(format!("{}, {}", msg, query), source),
+            Error::Decode(msg, source) => Error::Decode(format!("{}, {}", msg, query), source),
+            e => e,
+        }
+    }
+
+    /// the conn is broken or closed by the server, e.g. io error, mysql 2006/2013,
+    /// pg class 08/57P01. the statement may or may not have taken effect
+    pub fn is_connection_lost(&self) -> bool {
+        if let Error::Connection(_, _) = self {
+            return true;
+        }
+        let info = match self.db_info() {
+            Some(info) => info,
+            None => return false,
+        };
+        let state = info.sqlstate.as_deref().unwrap_or_default();
+        let code = info.vendor_code.unwrap_or_default();
+        match info.driver_type {
+            Some(DriverType::Postgres) => state.starts_with("08") || matches!(state, "57P01" | "57P02" | "57P03"),
+            //server has gone away, lost connection, server shutdown, connection killed, disconnected by server
+            Some(DriverType::Mysql) => matches!(code, 2006 | 2013 | 1053 | 1927 | 4031),
+            //azure sql: database not currently available, closed by the server
+            Some(DriverType::Mssql) => matches!(code, 40613 | 40197 | 10053 | 10054),
+            _ => false,
+        }
+    }
+
+    /// the statement failed without taking effect and running it (or the tx) again may succeed:
+    /// deadlock, serialization failure, lock timeout, sqlite SQLITE_BUSY/SQLITE_LOCKED,
+    /// pool timeout or too many connections
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Deadlock(_) | Error::SerializationFailure(_) | Error::PoolTimeout => return true,
+            _ => {}
+        }
+        let info = match self.db_info() {
+            Some(info) => info,
+            None => return false,
+        };
+        let state = info.sqlstate.as_deref().unwrap_or_default();
+        let code = info.vendor_code.unwrap_or_default();
+        match info.driver_type {
+            //serialization_failure, deadlock_detected, lock_not_available, too_many_connections
+            Some(DriverType::Postgres) => matches!(state, "40001" | "40P01" | "55P03" | "53300"),
+            //lock wait timeout, deadlock, too many connections
+            Some(DriverType::Mysql) => matches!(code, 1205 | 1213 | 1040),
+            //the primary result code of an extended code is the low 8 bits, 5 SQLITE_BUSY, 6 SQLITE_LOCKED
+            Some(DriverType::Sqlite) => matches!(code & 0xff, 5 | 6),
+            //deadlock, lock request timeout, snapshot conflict, azure sql throttling
+            Some(DriverType::Mssql) => matches!(code, 1205 | 1222 | 3960 | 40501 | 10928 | 10929 | 49918 | 49919 | 49920),
+            _ => false,
+        }
+    }
+
+    /// the error may go away by itself, is_retryable() or is_connection_lost()
+    pub fn is_transient(&self) -> bool {
+        self.is_retryable() || self.is_connection_lost()
+    }
 }
 
 impl Display for Error {
@@ -28,18 +226,39 @@ impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::E(error) => write!(f, "{}", error),
-            Error::Deserialize(error) => write!(f, "{}", error),
-            Error::Database(error) => write!(f, "{}", error.to_string()),
+            Error::Deserialize(error, _) => write!(f, "{}", error),
+            Error::Connection(error, _) => write!(f, "{}", error),
+            Error::PoolTimeout => write!(f, "pool timed out while waiting for an open connection"),
+            Error::PoolClosed => write!(f, "attempted to acquire a connection on a closed pool"),
+            Error::CircuitOpen => write!(f, "the circuit breaker of the pool is open"),
+            Error::Decode(error, _) => write!(f, "{}", error),
+            Error::Wrap(error, _) => write!(f, "{}", error),
+            Error::Database(info)
+            | Error::UniqueViolation(info)
+            | Error::ForeignKeyViolation(info)
+            | Error::Deadlock(info)
+            | Error::SerializationFailure(info) => write!(f, "error returned from database: {}", info),
         }
     }
 }
 
-impl StdError for Error {}
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        let source = match self {
+            Error::Deserialize(_, source)
+            | Error::Connection(_, source)
+            | Error::Decode(_, source)
+            | Error::Wrap(_, source) => source.as_ref(),
+            _ => self.db_info().and_then(|x| x.source.as_ref()),
+        };
+        source.map(|x| x.as_ref() as &(dyn StdError + 'static))
+    }
+}
 
 impl From<io::Error> for Error {
     #[inline]
     fn from(err: io::Error) -> Self {
-        Error::from(err.to_string())
+        Error::Wrap(err.to_string(), Some(Arc::new(err)))
     }
 }
 
@@ -63,33 +282,221 @@ impl From<&dyn std::error::Error> for Error {
 
 impl From<rexpr::error::Error> for crate::Error {
     fn from(e: rexpr::error::Error) -> Self {
-        crate::Error::E(format!("[rbatis]{}", e.to_string()))
+        crate::Error::Wrap(format!("[rbatis]{}", e), Some(Arc::new(e)))
     }
 }
 
 impl From<sqlx_core::error::BoxDynError> for crate::Error {
     fn from(arg: BoxDynError) -> Self {
-        return crate::Error::Database(arg.to_string());
+        match arg.downcast::<sqlx_core::error::Error>() {
+            Ok(e) => crate::Error::from(*e),
+            Err(arg) => crate::Error::Decode(arg.to_string(), Some(Arc::from(arg))),
+        }
     }
 }
 
 impl From<sqlx_core::error::Error> for crate::Error {
     fn from(arg: sqlx_core::error::Error) -> Self {
-        return crate::Error::Database(arg.to_string());
+        //the error of our hooks, see DBPool::pool_options()
+        if let sqlx_core::error::Error::Configuration(e) = &arg {
+            if let Some(e) = e.downcast_ref::<crate::Error>() {
+                return e.clone();
+            }
+        }
+        let message = arg.to_string();
+        match &arg {
+            sqlx_core::error::Error::Database(e) => {
+                let mut info = DBErrorInfo::from_database_error(e.as_ref());
+                info.source = Some(Arc::new(arg));
+                info.into_error()
+            }
+            sqlx_core::error::Error::Io(_)
+            | sqlx_core::error::Error::Tls(_)
+            | sqlx_core::error::Error::Protocol(_)
+            | sqlx_core::error::Error::WorkerCrashed => crate::Error::Connection(message, Some(Arc::new(arg))),
+            sqlx_core::error::Error::PoolTimedOut => crate::Error::PoolTimeout,
+            sqlx_core::error::Error::PoolClosed => crate::Error::PoolClosed,
+            sqlx_core::error::Error::TypeNotFound { .. }
+            | sqlx_core::error::Error::ColumnIndexOutOfBounds { .. }
+            | sqlx_core::error::Error::ColumnNotFound(_)
+            | sqlx_core::error::Error::ColumnDecode { .. }
+            | sqlx_core::error::Error::Decode(_) => crate::Error::Decode(message, Some(Arc::new(arg))),
+            _ => crate::Error::Wrap(message, Some(Arc::new(arg))),
+        }
     }
 }
 
-impl Clone for Error {
-    fn clone(&self) -> Self {
-        Error::from(self.to_string())
+impl From<serde_json::Error> for crate::Error {
+    fn from(arg: serde_json::Error) -> Self {
+        crate::Error::Deserialize(arg.to_string(), Some(Arc::new(arg)))
     }
+}
 
-    fn clone_from(&mut self, source: &Self) {
-        *self = Self::from(source.to_string());
+impl DBErrorInfo {
+    /// read the details of the driver error
+    pub fn from_database_error(e: &dyn DatabaseError) -> Self {
+        let mut info = DBErrorInfo {
+            driver_type: None,
+            message: e.message().to_string(),
+            sqlstate: None,
+            vendor_code: None,
+            constraint: e.constraint().map(|x| x.to_string()),
+            table: None,
+            column: None,
+            query: None,
+            source: None,
+        };
+        #[cfg(feature = "postgres")]
+        if let Some(e) = e.try_downcast_ref::<PgDatabaseError>() {
+            info.driver_type = Some(DriverType::Postgres);
+            info.sqlstate = Some(e.code().to_string());
+            info.table = e.table().map(|x| x.to_string());
+            info.column = e.column().map(|x| x.to_string());
+            return info;
+        }
+        #[cfg(feature = "mysql")]
+        if let Some(e) = e.try_downcast_ref::<MySqlDatabaseError>() {
+            info.driver_type = Some(DriverType::Mysql);
+            info.sqlstate = e.code().map(|x| x.to_string());
+            info.vendor_code = Some(e.number() as i64);
+            let msg = e.message();
+            //Duplicate entry '1' for key 't.PRIMARY'
+            //... a foreign key constraint fails (`db`.`child`, CONSTRAINT `fk` FOREIGN KEY (`pid`) REFERENCES ...
+            info.constraint = between(msg, "for key '", "'").or_else(|| between(msg, "CONSTRAINT `", "`"));
+            info.table = between(msg, "fails (", ", CONSTRAINT")
+                .and_then(|x| x.rsplit('.').next().map(|x| x.trim_matches('`').to_string()));
+            info.column = between(msg, "FOREIGN KEY (`", "`");
+            return info;
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(e) = e.try_downcast_ref::<SqliteError>() {
+            info.driver_type = Some(DriverType::Sqlite);
+            info.vendor_code = e.code().and_then(|x| x.parse().ok());
+            //UNIQUE constraint failed: t.id, t.name
+            if let Some(index) = e.message().find("constraint failed: ") {
+                let target = &e.message()[index + "constraint failed: ".len()..];
+                let first = target.split(", ").next().unwrap_or_default();
+                if let Some((table, column)) = first.split_once('.') {
+                    info.table = Some(table.to_string());
+                    info.column = Some(column.to_string());
+                }
+            }
+            return info;
+        }
+        #[cfg(feature = "mssql")]
+        if let Some(e) = e.try_downcast_ref::<MssqlDatabaseError>() {
+            info.driver_type = Some(DriverType::Mssql);
+            let msg = e.message();
+            //Violation of UNIQUE KEY constraint 'UQ_t'. Cannot insert duplicate key in object 'dbo.t'.
+            //... conflicted with the FOREIGN KEY constraint "FK_t". ... table "dbo.p", column 'id'.
+            info.constraint = between(msg, "constraint '", "'").or_else(|| between(msg, "constraint \"", "\""));
+            info.table = between(msg, "object '", "'").or_else(|| between(msg, "table \"", "\""));
+            info.column = between(msg, "column '", "'");
+            return info;
+        }
+        info
     }
+
+    /// wrap to the most specific Error variant
+    pub fn into_error(self) -> Error {
+        let state = self.sqlstate.as_deref().unwrap_or_default();
+        let code = self.vendor_code.unwrap_or_default();
+        let kind = match self.driver_type {
+            Some(DriverType::Postgres) => match state {
+                "23505" => ErrorKind::Unique,
+                "23503" => ErrorKind::ForeignKey,
+                "40P01" => ErrorKind::Deadlock,
+                "40001" => ErrorKind::Serialization,
+                _ => ErrorKind::Other,
+            },
+            Some(DriverType::Mysql) => match code {
+                1062 | 1586 => ErrorKind::Unique,
+                1216 | 1217 | 1451 | 1452 => ErrorKind::ForeignKey,
+                1213 => ErrorKind::Deadlock,
+                _ => ErrorKind::Other,
+            },
+            Some(DriverType::Sqlite) => match code {
+                //SQLITE_CONSTRAINT_UNIQUE, SQLITE_CONSTRAINT_PRIMARYKEY
+                2067 | 1555 => ErrorKind::Unique,
+                //SQLITE_CONSTRAINT_FOREIGNKEY
+                787 => ErrorKind::ForeignKey,
+                _ => ErrorKind::Other,
+            },
+            Some(DriverType::Mssql) => match code {
+                2627 | 2601 => ErrorKind::Unique,
+                //547 is also raised by CHECK constraints
+                547 if self.message.contains("FOREIGN KEY") => ErrorKind::ForeignKey,
+                1205 => ErrorKind::Deadlock,
+                3960 => ErrorKind::Serialization,
+                _ => ErrorKind::Other,
+            },
+            _ => ErrorKind::Other,
+        };
+        let info = Box::new(self);
+        match kind {
+            ErrorKind::Unique => Error::UniqueViolation(info),
+            ErrorKind::ForeignKey => Error::ForeignKeyViolation(info),
+            ErrorKind::Deadlock => Error::Deadlock(info),
+            ErrorKind::Serialization => Error::SerializationFailure(info),
+            ErrorKind::Other => Error::Database(info),
+        }
+    }
+}
+
+enum ErrorKind {
+    Unique,
+    ForeignKey,
+    Deadlock,
+    Serialization,
+    Other,
+}
+
+/// the text between start and the next end
+fn between(s: &str, start: &str, end: &str) -> Option<String> {
+    let begin = s.find(start)? + start.len();
+    let len = s[begin..].find(end)?;
+    Some(s[begin..begin + len].to_string())
+}
+
+/// the structured form of Error, the source is not serialized
+#[derive(Serialize, Deserialize)]
+struct ErrorRepr {
+    kind: String,
+    message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    info: Option<Box<DBErrorInfo>>,
+}
+
+/// a plain string is the default serialized form of Error
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ErrorForm {
+    Message(String),
+    Repr(ErrorRepr),
 }
 
-// This is what #[derive(Serialize)] would generate.
+impl Error {
+    /// the variant name
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::E(_) => "E",
+            Error::Deserialize(_, _) => "Deserialize",
+            Error::Database(_) => "Database",
+            Error::Connection(_, _) => "Connection",
+            Error::PoolTimeout => "PoolTimeout",
+            Error::PoolClosed => "PoolClosed",
+            Error::CircuitOpen => "CircuitOpen",
+            Error::UniqueViolation(_) => "UniqueViolation",
+            Error::ForeignKeyViolation(_) => "ForeignKeyViolation",
+            Error::Deadlock(_) => "Deadlock",
+            Error::SerializationFailure(_) => "SerializationFailure",
+            Error::Decode(_, _) => "Decode",
+            Error::Wrap(_, _) => "Wrap",
+        }
+    }
+}
+
+/// serialized as the message string, use error::structured to keep the variant and the db_info()
 impl Serialize for Error {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
         where
@@ -99,37 +506,71 @@ impl Serialize for Error {
     }
 }
 
-struct ErrorVisitor;
-
-impl<'de> Visitor<'de> for ErrorVisitor {
-    type Value = String;
-
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("a string")
-    }
-
-    fn visit_string<E>(self, v: String) -> std::result::Result<Self::Value, E>
+/// read the message string, or the structured form written by error::structured
+impl<'de> Deserialize<'de> for Error {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
         where
-            E: std::error::Error,
+            D: Deserializer<'de>,
     {
-        Ok(v)
+        let repr = match ErrorForm::deserialize(deserializer)? {
+            ErrorForm::Message(message) => {
+                return Ok(Error::from(message));
+            }
+            ErrorForm::Repr(repr) => repr,
+        };
+        let message = repr.message;
+        let info = repr.info.unwrap_or_else(|| Box::new(DBErrorInfo {
+            message: message.clone(),
+            ..Default::default()
+        }));
+        Ok(match repr.kind.as_str() {
+            "Deserialize" => Error::Deserialize(message, None),
+            "Database" => Error::Database(info),
+            "Connection" => Error::Connection(message, None),
+            "PoolTimeout" => Error::PoolTimeout,
+            "PoolClosed" => Error::PoolClosed,
+            "CircuitOpen" => Error::CircuitOpen,
+            "UniqueViolation" => Error::UniqueViolation(info),
+            "ForeignKeyViolation" => Error::ForeignKeyViolation(info),
+            "Deadlock" => Error::Deadlock(info),
+            "SerializationFailure" => Error::SerializationFailure(info),
+            "Decode" => Error::Decode(message, None),
+            "Wrap" => Error::Wrap(message, None),
+            _ => Error::E(message),
+        })
     }
+}
+
+/// opt-in structured serde form of Error, a {kind, message, info} object keeping the variant and the db_info().
+/// ```ignore
+/// #[derive(Serialize, Deserialize)]
+/// struct Reply {
+///     #[serde(with = "rbatis_core::error::structured")]
+///     error: rbatis_core::Error,
+/// }
+/// ```
+pub mod structured {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{Error, ErrorRepr};
 
-    fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E>
+    pub fn serialize<S>(e: &Error, serializer: S) -> std::result::Result<S::Ok, S::Error>
         where
-            E: std::error::Error,
+            S: Serializer,
     {
-        Ok(v.to_string())
+        ErrorRepr {
+            kind: e.kind().to_string(),
+            message: e.to_string(),
+            info: e.db_info().cloned().map(Box::new),
+        }.serialize(serializer)
     }
-}
 
-impl<'de> Deserialize<'de> for Error {
-    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    /// also read the plain string form
+    pub fn deserialize<'de, D>(deserializer: D) -> std::result::Result<Error, D::Error>
         where
             D: Deserializer<'de>,
     {
-        let r = deserializer.deserialize_string(ErrorVisitor)?;
-        return Ok(Error::from(r));
+        Error::deserialize(deserializer)
     }
 }
 
@@ -149,7 +590,7 @@ impl<T> OptionToResult<T> for Option<T> {
 
 impl From<py_sql::error::Error> for crate::Error {
     fn from(arg: py_sql::error::Error) -> Error {
-        Error::E(arg.to_string())
+        Error::Wrap(arg.to_string(), Some(Arc::new(arg)))
     }
 }
 
@@ -161,7 +602,7 @@ impl Into<py_sql::error::Error> for crate::Error {
 
 impl From<Error> for std::io::Error {
     fn from(arg: Error) -> Self {
-        arg.into()
+        std::io::Error::other(arg)
     }
 }
 
@@ -171,3 +612,90 @@ fn test_json_error() {
     let s = serde_json::to_string(&e).unwrap();
     println!("{}", s.as_str());
 }
+
+#[test]
+fn test_json_error_round_trip() {
+    #[derive(Serialize, Deserialize)]
+    struct Reply {
+        #[serde(with = "structured")]
+        error: Error,
+    }
+    let e = DBErrorInfo {
+        driver_type: Some(DriverType::Postgres),
+        message: "deadlock detected".to_string(),
+        sqlstate: Some("40P01".to_string()),
+        ..Default::default()
+    }.into_error();
+    assert_eq!(serde_json::to_string(&e).unwrap(), "\"error returned from database: 40P01: deadlock detected\"");
+    let s = serde_json::to_string(&Reply { error: e }).unwrap();
+    let e = serde_json::from_str::<Reply>(&s).unwrap().error;
+    assert_eq!(e.db_info().unwrap().sqlstate, Some("40P01".to_string()));
+    assert!(matches!(e.clone(), Error::Deadlock(_)));
+    let e: Error = serde_json::from_str("\"old\"").unwrap();
+    assert!(matches!(e, Error::E(_)));
+}
+
+#[test]
+fn test_error_source() {
+    let json_err = serde_json::from_str::<i32>("x").unwrap_err();
+    let e = Error::from(json_err);
+    assert!(e.source().unwrap().downcast_ref::<serde_json::Error>().is_some());
+    assert!(e.clone().source().is_some());
+}
+
+#[test]
+fn test_into_error() {
+    let info = DBErrorInfo {
+        driver_type: Some(DriverType::Mysql),
+        message: "Duplicate entry '1' for key 't.PRIMARY'".to_string(),
+        sqlstate: Some("23000".to_string()),
+        vendor_code: Some(1062),
+        ..Default::default()
+    };
+    assert_eq!(info.to_string(), "1062 (23000): Duplicate entry '1' for key 't.PRIMARY'");
+    assert!(matches!(info.into_error(), Error::UniqueViolation(_)));
+    assert_eq!(between("fails (`db`.`child`, CONSTRAINT `fk`", "CONSTRAINT `", "`"), Some("fk".to_string()));
+}
+
+#[test]
+fn test_transient_error() {
+    let info = |driver_type, sqlstate: Option<&str>, vendor_code: Option<i64>| DBErrorInfo {
+        driver_type: Some(driver_type),
+        sqlstate: sqlstate.map(|x| x.to_string()),
+        vendor_code,
+        ..Default::default()
+    };
+    let busy = info(DriverType::Sqlite, None, Some(5)).into_error();
+    assert!(busy.is_retryable() && busy.is_transient() && !busy.is_connection_lost());
+    //SQLITE_BUSY_SNAPSHOT
+    assert!(info(DriverType::Sqlite, None, Some(517)).into_error().is_retryable());
+    assert!(info(DriverType::Mysql, Some("HY000"), Some(1205)).into_error().is_retryable());
+    let gone = info(DriverType::Mysql, Some("HY000"), Some(2006)).into_error();
+    assert!(gone.is_connection_lost() && gone.is_transient() && !gone.is_retryable());
+    assert!(info(DriverType::Postgres, Some("08006"), None).into_error().is_connection_lost());
+    assert!(info(DriverType::Postgres, Some("40001"), None).into_error().is_retryable());
+    assert!(info(DriverType::Mssql, None, Some(1205)).into_error().is_retryable());
+    let unique = info(DriverType::Postgres, Some("23505"), None).into_error();
+    assert!(!unique.is_transient());
+    assert!(Error::Connection("broken pipe".to_string(), None).is_connection_lost());
+    assert!(!Error::from("database is locked").is_transient());
+}
+
+#[test]
+fn test_sqlite_constraint_error() {
+    crate::runtime::task::block_on(async {
+        let pool = crate::db::DBPool::new("sqlite::memory:").await.unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+        conn.exec("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT UNIQUE)").await.unwrap();
+        conn.exec("INSERT INTO t VALUES (1, 'a')").await.unwrap();
+        let e = conn.exec("INSERT INTO t VALUES (2, 'a')").await.unwrap_err();
+        assert!(e.source().unwrap().downcast_ref::<sqlx_core::error::Error>().is_some());
+        match e {
+            Error::UniqueViolation(info) => {
+                assert_eq!(info.table, Some("t".to_string()));
+                assert_eq!(info.column, Some("name".to_string()));
+            }
+            _ => panic!("not UniqueViolation: {:?}", e),
+        }
+    });
+}
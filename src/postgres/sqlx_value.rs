@@ -216,18 +216,24 @@ impl<'c> JsonCodec for PgValueRef<'c> {
     }
 }
 
+impl RefJsonCodec for PgRow {
+    fn try_to_json(&self) -> crate::Result<serde_json::Value> {
+        let mut m = serde_json::Map::new();
+        let columns = self.columns();
+        for x in columns {
+            let key = x.name();
+            let v: PgValueRef = self.try_get_raw(key)?;
+            m.insert(key.to_owned(), v.try_to_json()?);
+        }
+        Ok(serde_json::Value::Object(m))
+    }
+}
+
 impl RefJsonCodec for Vec<PgRow> {
     fn try_to_json(&self) -> crate::Result<serde_json::Value> {
         let mut arr = Vec::with_capacity(self.len());
         for row in self {
-            let mut m = serde_json::Map::new();
-            let columns = row.columns();
-            for x in columns {
-                let key = x.name();
-                let v: PgValueRef = row.try_get_raw(key)?;
-                m.insert(key.to_owned(), v.try_to_json()?);
-            }
-            arr.push(serde_json::Value::Object(m));
+            arr.push(row.try_to_json()?);
         }
         Ok(serde_json::Value::from(arr))
     }
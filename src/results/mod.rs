@@ -7,10 +7,10 @@ impl<T> ResultCodec<T> for Result<T, BoxDynError> {
     fn into_result(self) -> crate::Result<T> {
         match self {
             Ok(t) => {
-                return Ok(t);
+                Ok(t)
             }
             Err(e) => {
-                return Err(crate::Error::from(e.to_string()));
+                Err(crate::Error::from(e))
             }
         }
     }
@@ -21,10 +21,10 @@ impl<T> ResultCodec<T> for Result<T, sqlx_core::error::Error> {
     fn into_result(self) -> crate::Result<T> {
         match self {
             Ok(t) => {
-                return Ok(t);
+                Ok(t)
             }
             Err(e) => {
-                return Err(crate::Error::from(e.to_string()));
+                Err(crate::Error::from(e))
             }
         }
     }
@@ -35,10 +35,10 @@ impl<T> ResultCodec<T> for Result<T, serde_json::Error> {
     fn into_result(self) -> crate::Result<T> {
         match self {
             Ok(t) => {
-                return Ok(t);
+                Ok(t)
             }
             Err(e) => {
-                return Err(crate::Error::from(e.to_string()));
+                Err(crate::Error::from(e))
             }
         }
     }